@@ -8,7 +8,12 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use core::atomic::{AtomicUint, INIT_ATOMIC_UINT, Ordering};
+use core::mem;
+use core::option::Option;
 use core::ptr::PtrExt;
+use core::slice::SliceExt;
+use libc;
 
 // FIXME: #13996: mark the `allocate` and `reallocate` return value as `noalias`
 
@@ -21,7 +26,7 @@ use core::ptr::PtrExt;
 /// size on the platform.
 #[inline]
 pub unsafe fn allocate(size: uint, align: uint) -> *mut u8 {
-    imp::allocate(size, align)
+    Heap.allocate(size, align)
 }
 
 /// Resize the allocation referenced by `ptr` to `size` bytes.
@@ -37,7 +42,7 @@ pub unsafe fn allocate(size: uint, align: uint) -> *mut u8 {
 /// any value in range_inclusive(requested_size, usable_size).
 #[inline]
 pub unsafe fn reallocate(ptr: *mut u8, old_size: uint, size: uint, align: uint) -> *mut u8 {
-    imp::reallocate(ptr, old_size, size, align)
+    Heap.reallocate(ptr, old_size, size, align)
 }
 
 /// Resize the allocation referenced by `ptr` to `size` bytes.
@@ -54,7 +59,7 @@ pub unsafe fn reallocate(ptr: *mut u8, old_size: uint, size: uint, align: uint)
 /// any value in range_inclusive(requested_size, usable_size).
 #[inline]
 pub unsafe fn reallocate_inplace(ptr: *mut u8, old_size: uint, size: uint, align: uint) -> uint {
-    imp::reallocate_inplace(ptr, old_size, size, align)
+    Heap.reallocate_inplace(ptr, old_size, size, align)
 }
 
 /// Deallocates the memory referenced by `ptr`.
@@ -66,14 +71,19 @@ pub unsafe fn reallocate_inplace(ptr: *mut u8, old_size: uint, size: uint, align
 /// any value in range_inclusive(requested_size, usable_size).
 #[inline]
 pub unsafe fn deallocate(ptr: *mut u8, old_size: uint, align: uint) {
-    imp::deallocate(ptr, old_size, align)
+    Heap.deallocate(ptr, old_size, align)
 }
 
 /// Returns the usable size of an allocation created with the specified the
 /// `size` and `align`.
+///
+/// On some backends (unix, windows) this is not a pure query when there is no
+/// live allocation to ask about: it allocates and immediately frees a block to
+/// find out, so it should not be polled from a hot path such as a repeated
+/// grow-capacity check.
 #[inline]
 pub fn usable_size(size: uint, align: uint) -> uint {
-    imp::usable_size(size, align)
+    Heap.usable_size(size, align)
 }
 
 /// Prints implementation-defined allocator statistics.
@@ -82,7 +92,62 @@ pub fn usable_size(size: uint, align: uint) -> uint {
 /// during the call.
 #[unstable]
 pub fn stats_print() {
-    imp::stats_print();
+    Heap.stats_print();
+}
+
+/// Return a pointer to `size` bytes of zeroed memory aligned to `align`.
+///
+/// On failure, return a null pointer.
+///
+/// This is equivalent to `allocate` followed by zeroing the returned buffer, but
+/// backends that can hand back already-zeroed pages (e.g. jemalloc's `MALLOCX_ZERO`)
+/// do so in one step instead of paying for a separate `memset`.
+///
+/// Behavior is undefined if the requested size is 0 or the alignment is not a
+/// power of 2. The alignment must be no larger than the largest supported page
+/// size on the platform.
+#[inline]
+pub unsafe fn allocate_zeroed(size: uint, align: uint) -> *mut u8 {
+    Heap.allocate_zeroed(size, align)
+}
+
+/// Resize the allocation referenced by `ptr` to `size` bytes, zeroing any newly
+/// added bytes.
+///
+/// On failure, return a null pointer and leave the original allocation intact.
+///
+/// Behavior is undefined if the requested size is 0 or the alignment is not a
+/// power of 2. The alignment must be no larger than the largest supported page
+/// size on the platform.
+///
+/// The `old_size` and `align` parameters are the parameters that were used to
+/// create the allocation referenced by `ptr`. The `old_size` parameter may be
+/// any value in range_inclusive(requested_size, usable_size).
+#[inline]
+pub unsafe fn reallocate_zeroed(ptr: *mut u8, old_size: uint, size: uint, align: uint) -> *mut u8 {
+    Heap.reallocate_zeroed(ptr, old_size, size, align)
+}
+
+/// A snapshot of allocator-wide memory usage, in bytes.
+pub struct Stats {
+    /// Bytes allocated by the application.
+    pub allocated: uint,
+    /// Bytes in active pages allocated by the application, a multiple of the page size.
+    pub active: uint,
+    /// Bytes in extents mapped by the allocator, a multiple of the page size.
+    pub mapped: uint,
+    /// Bytes in physically resident data pages mapped by the allocator.
+    pub resident: uint,
+}
+
+/// Returns a snapshot of allocator-wide statistics, or `None` if the active
+/// backend does not support programmatic introspection (only jemalloc does
+/// today; `stats_print` remains available everywhere).
+///
+/// This lets services sample heap usage over time without scraping the
+/// human-readable dump produced by `stats_print`.
+pub fn stats() -> Option<Stats> {
+    imp::stats()
 }
 
 /// An arbitrary non-null address to represent zero-size allocations.
@@ -91,6 +156,278 @@ pub fn stats_print() {
 /// non-zero-size memory allocations.
 pub const EMPTY: *mut () = 0x1 as *mut ();
 
+/// A memory allocator, abstracting over the free functions above.
+///
+/// The free functions (`allocate`, `reallocate`, ...) dispatch to the default,
+/// `cfg`-selected backend via the zero-sized `Heap` type below. Implementing this trait
+/// for another type lets a collection be parameterized over a non-default allocator
+/// (an arena, a bump allocator, a counting wrapper for tests) without requiring a
+/// custom libstd build.
+pub trait Allocator {
+    /// Return a pointer to `size` bytes of memory aligned to `align`.
+    ///
+    /// On failure, return a null pointer.
+    ///
+    /// Behavior is undefined if the requested size is 0 or the alignment is not a
+    /// power of 2. The alignment must be no larger than the largest supported page
+    /// size on the platform.
+    unsafe fn allocate(&self, size: uint, align: uint) -> *mut u8;
+
+    /// Resize the allocation referenced by `ptr` to `size` bytes.
+    ///
+    /// On failure, return a null pointer and leave the original allocation intact.
+    ///
+    /// Behavior is undefined if the requested size is 0 or the alignment is not a
+    /// power of 2. The alignment must be no larger than the largest supported page
+    /// size on the platform.
+    ///
+    /// The `old_size` and `align` parameters are the parameters that were used to
+    /// create the allocation referenced by `ptr`. The `old_size` parameter may be
+    /// any value in range_inclusive(requested_size, usable_size).
+    unsafe fn reallocate(&self, ptr: *mut u8, old_size: uint, size: uint, align: uint) -> *mut u8;
+
+    /// Resize the allocation referenced by `ptr` to `size` bytes.
+    ///
+    /// If the operation succeeds, it returns a usable size of at least `size` for
+    /// the allocation; if it fails, it returns a size of at least `old_size`.
+    /// For `Heap` on jemalloc these are `usable_size(size, align)` and
+    /// `usable_size(old_size, align)`; on the unix and windows backends success
+    /// returns the existing block's full usable size (the block is never shrunk)
+    /// and failure returns `old_size` itself.
+    ///
+    /// Behavior is undefined if the requested size is 0 or the alignment is not a
+    /// power of 2. The alignment must be no larger than the largest supported page
+    /// size on the platform.
+    ///
+    /// The `old_size` and `align` parameters are the parameters that were used to
+    /// create the allocation referenced by `ptr`. The `old_size` parameter may be
+    /// any value in range_inclusive(requested_size, usable_size).
+    unsafe fn reallocate_inplace(&self, ptr: *mut u8, old_size: uint, size: uint,
+                                  align: uint) -> uint;
+
+    /// Deallocates the memory referenced by `ptr`.
+    ///
+    /// The `ptr` parameter must not be null.
+    ///
+    /// The `old_size` and `align` parameters are the parameters that were used to
+    /// create the allocation referenced by `ptr`. The `old_size` parameter may be
+    /// any value in range_inclusive(requested_size, usable_size).
+    unsafe fn deallocate(&self, ptr: *mut u8, old_size: uint, align: uint);
+
+    /// Returns the usable size of an allocation created with the specified the
+    /// `size` and `align`.
+    ///
+    /// Implementations that have no live allocation to query (e.g. the unix and
+    /// windows backends behind `Heap`) may need to allocate and deallocate a probe
+    /// block to answer this, so callers should not treat it as a free operation.
+    fn usable_size(&self, size: uint, align: uint) -> uint;
+
+    /// Prints implementation-defined allocator statistics.
+    ///
+    /// These statistics may be inconsistent if other threads use the allocator
+    /// during the call.
+    fn stats_print(&self);
+
+    /// Return a pointer to `size` bytes of zeroed memory aligned to `align`.
+    ///
+    /// On failure, return a null pointer. See `allocate` for the safety contract.
+    unsafe fn allocate_zeroed(&self, size: uint, align: uint) -> *mut u8;
+
+    /// Resize the allocation referenced by `ptr` to `size` bytes, zeroing any
+    /// newly added bytes.
+    ///
+    /// On failure, return a null pointer and leave the original allocation intact.
+    /// See `reallocate` for the safety contract.
+    unsafe fn reallocate_zeroed(&self, ptr: *mut u8, old_size: uint, size: uint,
+                                 align: uint) -> *mut u8;
+}
+
+/// The default allocator: dispatches to whichever backend was selected via `cfg`
+/// (`jemalloc`, `external_funcs`, `external_crate`, `unix`, `windows`).
+///
+/// `Heap` is a zero-sized unit struct, used as the default allocator type parameter
+/// for collections generic over `Allocator` (see `RawBuf` below) at no cost over
+/// calling the free functions directly.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Heap;
+
+impl Allocator for Heap {
+    #[inline]
+    unsafe fn allocate(&self, size: uint, align: uint) -> *mut u8 {
+        imp::allocate(size, align)
+    }
+
+    #[inline]
+    unsafe fn reallocate(&self, ptr: *mut u8, old_size: uint, size: uint, align: uint) -> *mut u8 {
+        imp::reallocate(ptr, old_size, size, align)
+    }
+
+    #[inline]
+    unsafe fn reallocate_inplace(&self, ptr: *mut u8, old_size: uint, size: uint,
+                                  align: uint) -> uint {
+        imp::reallocate_inplace(ptr, old_size, size, align)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: *mut u8, old_size: uint, align: uint) {
+        imp::deallocate(ptr, old_size, align)
+    }
+
+    #[inline]
+    fn usable_size(&self, size: uint, align: uint) -> uint {
+        imp::usable_size(size, align)
+    }
+
+    fn stats_print(&self) {
+        imp::stats_print()
+    }
+
+    #[inline]
+    unsafe fn allocate_zeroed(&self, size: uint, align: uint) -> *mut u8 {
+        imp::allocate_zeroed(size, align)
+    }
+
+    #[inline]
+    unsafe fn reallocate_zeroed(&self, ptr: *mut u8, old_size: uint, size: uint,
+                                 align: uint) -> *mut u8 {
+        imp::reallocate_zeroed(ptr, old_size, size, align)
+    }
+}
+
+/// A minimal generic buffer, parameterized over `A: Allocator`, defaulting to `Heap`.
+///
+/// This crate slice has no `Vec`/`RawVec` to wire up for real, so `RawBuf` stands in
+/// as the smallest possible demonstration of the hook the `Allocator` trait exists
+/// for: a collection holds its allocator as a field and threads it through its own
+/// `allocate`/`deallocate` calls instead of going through the free functions above.
+pub struct RawBuf<T, A: Allocator = Heap> {
+    ptr: *mut T,
+    cap: uint,
+    alloc: A,
+}
+
+impl<T, A: Allocator> RawBuf<T, A> {
+    /// Creates a `RawBuf` with no allocation, using `alloc` for any future growth.
+    pub fn new_in(alloc: A) -> RawBuf<T, A> {
+        RawBuf { ptr: EMPTY as *mut T, cap: 0, alloc: alloc }
+    }
+
+    /// Allocates room for `cap` elements of `T` through `alloc`.
+    pub fn with_capacity_in(cap: uint, alloc: A) -> RawBuf<T, A> {
+        if cap == 0 || mem::size_of::<T>() == 0 {
+            return RawBuf::new_in(alloc);
+        }
+        let size = cap * mem::size_of::<T>();
+        let align = mem::min_align_of::<T>();
+        let ptr = unsafe { alloc.allocate(size, align) };
+        if ptr.is_null() {
+            run_oom_hook(size, align);
+            ::oom()
+        }
+        RawBuf { ptr: ptr as *mut T, cap: cap, alloc: alloc }
+    }
+
+    pub fn ptr(&self) -> *mut T { self.ptr }
+
+    pub fn cap(&self) -> uint { self.cap }
+
+    /// Releases the buffer's storage back to its allocator.
+    ///
+    /// Not a `Drop` impl: generic destructors over a type parameter like `A` need
+    /// `#[unsafe_destructor]` in this era, which this minimal demonstration skips by
+    /// requiring the owner to call this explicitly instead.
+    pub unsafe fn dealloc(&mut self) {
+        if self.cap != 0 && mem::size_of::<T>() != 0 {
+            let size = self.cap * mem::size_of::<T>();
+            let align = mem::min_align_of::<T>();
+            self.alloc.deallocate(self.ptr as *mut u8, size, align);
+            self.cap = 0;
+        }
+    }
+}
+
+/// A hook run when allocation fails, before the runtime aborts.
+///
+/// The hook receives the `size` and `align` that could not be satisfied, giving
+/// applications a chance to log them, flush telemetry, or dump `stats()` above.
+pub type OomHook = fn(size: uint, align: uint);
+
+static OOM_HOOK: AtomicUint = INIT_ATOMIC_UINT;
+
+/// Registers `hook` to run the next time an allocation fails.
+///
+/// Only one hook may be registered at a time; registering a new one replaces
+/// the previous one. There is no way to unregister back to the default short
+/// of registering `default_oom_hook` again.
+pub fn set_oom_hook(hook: OomHook) {
+    OOM_HOOK.store(hook as uint, Ordering::SeqCst);
+}
+
+/// The default out-of-memory hook: prints the failed `size`/`align` to stderr.
+///
+/// Formats by hand into a fixed-size stack buffer instead of going through
+/// `core::fmt`, since this path must not allocate.
+pub fn default_oom_hook(size: uint, align: uint) {
+    let mut buf = [0u8; 96];
+    let mut len = 0u;
+    len = append_bytes(&mut buf, len, b"fatal runtime error: allocation of ");
+    len = append_uint(&mut buf, len, size);
+    len = append_bytes(&mut buf, len, b" bytes (align ");
+    len = append_uint(&mut buf, len, align);
+    len = append_bytes(&mut buf, len, b") failed\n");
+    unsafe {
+        // fd 2 is stderr.
+        libc::write(2, buf.as_ptr() as *const libc::c_void, len as libc::size_t);
+    }
+}
+
+fn append_bytes(buf: &mut [u8; 96], pos: uint, src: &[u8]) -> uint {
+    let mut pos = pos;
+    let mut i = 0u;
+    while i < src.len() && pos < buf.len() {
+        buf[pos] = src[i];
+        pos += 1;
+        i += 1;
+    }
+    pos
+}
+
+fn append_uint(buf: &mut [u8; 96], pos: uint, mut value: uint) -> uint {
+    if value == 0 {
+        return append_bytes(buf, pos, b"0");
+    }
+    let mut digits = [0u8; 20];
+    let mut n_digits = 0u;
+    while value > 0 {
+        digits[n_digits] = b'0' + (value % 10) as u8;
+        value /= 10;
+        n_digits += 1;
+    }
+    let mut pos = pos;
+    while n_digits > 0 && pos < buf.len() {
+        n_digits -= 1;
+        buf[pos] = digits[n_digits];
+        pos += 1;
+    }
+    pos
+}
+
+/// Runs the registered OOM hook (or `default_oom_hook` if none was set).
+///
+/// Cold and never inlined: this path is only reached once allocation has
+/// already failed, so it must add no cost to the success path.
+#[cold]
+#[inline(never)]
+fn run_oom_hook(size: uint, align: uint) {
+    let hook = OOM_HOOK.load(Ordering::SeqCst);
+    let hook: OomHook = if hook == 0 {
+        default_oom_hook
+    } else {
+        unsafe { mem::transmute(hook) }
+    };
+    hook(size, align);
+}
+
 /// The allocator for unique pointers.
 #[cfg(not(test))]
 #[lang="exchange_malloc"]
@@ -100,7 +437,10 @@ unsafe fn exchange_malloc(size: uint, align: uint) -> *mut u8 {
         EMPTY as *mut u8
     } else {
         let ptr = allocate(size, align);
-        if ptr.is_null() { ::oom() }
+        if ptr.is_null() {
+            run_oom_hook(size, align);
+            ::oom()
+        }
         ptr
     }
 }
@@ -125,10 +465,16 @@ const MIN_ALIGN: uint = 16;
 
 #[cfg(external_funcs)]
 mod imp {
+    use core::option::Option;
+    use core::option::Option::None;
+
     extern {
         fn rust_allocate(size: uint, align: uint) -> *mut u8;
+        fn rust_allocate_zeroed(size: uint, align: uint) -> *mut u8;
         fn rust_deallocate(ptr: *mut u8, old_size: uint, align: uint);
         fn rust_reallocate(ptr: *mut u8, old_size: uint, size: uint, align: uint) -> *mut u8;
+        fn rust_reallocate_zeroed(ptr: *mut u8, old_size: uint, size: uint,
+                                  align: uint) -> *mut u8;
         fn rust_reallocate_inplace(ptr: *mut u8, old_size: uint, size: uint,
                                    align: uint) -> uint;
         fn rust_usable_size(size: uint, align: uint) -> uint;
@@ -140,6 +486,17 @@ mod imp {
         rust_allocate(size, align)
     }
 
+    #[inline]
+    pub unsafe fn allocate_zeroed(size: uint, align: uint) -> *mut u8 {
+        rust_allocate_zeroed(size, align)
+    }
+
+    #[inline]
+    pub unsafe fn reallocate_zeroed(ptr: *mut u8, old_size: uint, size: uint,
+                                    align: uint) -> *mut u8 {
+        rust_reallocate_zeroed(ptr, old_size, size, align)
+    }
+
     #[inline]
     pub unsafe fn reallocate_inplace(ptr: *mut u8, old_size: uint, size: uint,
                                      align: uint) -> uint {
@@ -166,19 +523,25 @@ mod imp {
     pub fn stats_print() {
         unsafe { rust_stats_print() }
     }
+
+    #[inline]
+    pub fn stats() -> Option<super::Stats> {
+        None
+    }
 }
 
 #[cfg(external_crate)]
 mod imp {
     extern crate external;
-    pub use self::external::{allocate, deallocate, reallocate_inplace, reallocate};
-    pub use self::external::{usable_size, stats_print};
+    pub use self::external::{allocate, allocate_zeroed, deallocate, reallocate_inplace};
+    pub use self::external::{reallocate, reallocate_zeroed, usable_size, stats_print, stats};
 }
 
 #[cfg(all(not(external_funcs), not(external_crate), jemalloc))]
 mod imp {
+    use core::mem;
     use core::option::Option;
-    use core::option::Option::None;
+    use core::option::Option::{None, Some};
     use core::ptr::{null_mut, null};
     use core::num::Int;
     use libc::{c_char, c_int, c_void, size_t};
@@ -198,6 +561,8 @@ mod imp {
                                                                 *const c_char)>,
                                  cbopaque: *mut c_void,
                                  opts: *const c_char);
+        fn je_mallctl(name: *const c_char, oldp: *mut c_void, oldlenp: *mut size_t,
+                      newp: *mut c_void, newlen: size_t) -> c_int;
     }
 
     // -lpthread needs to occur after -ljemalloc, the earlier argument isn't enough
@@ -209,6 +574,9 @@ mod imp {
     #[inline(always)]
     fn mallocx_align(a: uint) -> c_int { a.trailing_zeros() as c_int }
 
+    // MALLOCX_ZERO macro
+    const MALLOCX_ZERO: c_int = 0x40;
+
     #[inline(always)]
     fn align_to_flags(align: uint) -> c_int {
         if align <= MIN_ALIGN { 0 } else { mallocx_align(align) }
@@ -220,12 +588,25 @@ mod imp {
         je_mallocx(size as size_t, flags) as *mut u8
     }
 
+    #[inline]
+    pub unsafe fn allocate_zeroed(size: uint, align: uint) -> *mut u8 {
+        let flags = align_to_flags(align) | MALLOCX_ZERO;
+        je_mallocx(size as size_t, flags) as *mut u8
+    }
+
     #[inline]
     pub unsafe fn reallocate(ptr: *mut u8, _old_size: uint, size: uint, align: uint) -> *mut u8 {
         let flags = align_to_flags(align);
         je_rallocx(ptr as *mut c_void, size as size_t, flags) as *mut u8
     }
 
+    #[inline]
+    pub unsafe fn reallocate_zeroed(ptr: *mut u8, _old_size: uint, size: uint,
+                                    align: uint) -> *mut u8 {
+        let flags = align_to_flags(align) | MALLOCX_ZERO;
+        je_rallocx(ptr as *mut c_void, size as size_t, flags) as *mut u8
+    }
+
     #[inline]
     pub unsafe fn reallocate_inplace(ptr: *mut u8, _old_size: uint, size: uint,
                                      align: uint) -> uint {
@@ -250,11 +631,44 @@ mod imp {
             je_malloc_stats_print(None, null_mut(), null())
         }
     }
+
+    // Ask jemalloc to refresh the cached statistics returned by `stats.*` mallctls.
+    unsafe fn refresh_stats() {
+        let mut epoch: u64 = 1;
+        let mut epoch_len = mem::size_of::<u64>() as size_t;
+        je_mallctl(b"epoch\0".as_ptr() as *const c_char,
+                   &mut epoch as *mut u64 as *mut c_void, &mut epoch_len as *mut size_t,
+                   &mut epoch as *mut u64 as *mut c_void, epoch_len);
+    }
+
+    // Returns `None` if jemalloc couldn't answer the mib (e.g. built without
+    // --enable-stats), rather than silently handing back a zeroed reading.
+    unsafe fn mallctl_read(name: &[u8]) -> Option<uint> {
+        let mut value: size_t = 0;
+        let mut len = mem::size_of::<size_t>() as size_t;
+        let ret = je_mallctl(name.as_ptr() as *const c_char,
+                              &mut value as *mut size_t as *mut c_void, &mut len as *mut size_t,
+                              null_mut(), 0);
+        if ret == 0 { Some(value as uint) } else { None }
+    }
+
+    pub fn stats() -> Option<super::Stats> {
+        unsafe {
+            refresh_stats();
+            let allocated = match mallctl_read(b"stats.allocated\0") { Some(v) => v, None => return None };
+            let active = match mallctl_read(b"stats.active\0") { Some(v) => v, None => return None };
+            let mapped = match mallctl_read(b"stats.mapped\0") { Some(v) => v, None => return None };
+            let resident = match mallctl_read(b"stats.resident\0") { Some(v) => v, None => return None };
+            Some(super::Stats { allocated: allocated, active: active, mapped: mapped, resident: resident })
+        }
+    }
 }
 
 #[cfg(all(not(external_funcs), not(external_crate), not(jemalloc), unix))]
 mod imp {
     use core::cmp;
+    use core::option::Option;
+    use core::option::Option::None;
     use core::ptr;
     use libc;
     use super::MIN_ALIGN;
@@ -263,6 +677,7 @@ mod imp {
         fn posix_memalign(memptr: *mut *mut libc::c_void,
                           align: libc::size_t,
                           size: libc::size_t) -> libc::c_int;
+        fn malloc_usable_size(ptr: *const libc::c_void) -> libc::size_t;
     }
 
     #[inline]
@@ -282,6 +697,19 @@ mod imp {
         }
     }
 
+    #[inline]
+    pub unsafe fn allocate_zeroed(size: uint, align: uint) -> *mut u8 {
+        if align <= MIN_ALIGN {
+            libc::calloc(size as libc::size_t, 1) as *mut u8
+        } else {
+            let new_ptr = allocate(size, align);
+            if !new_ptr.is_null() {
+                ptr::zero_memory(new_ptr, size);
+            }
+            new_ptr
+        }
+    }
+
     #[inline]
     pub unsafe fn reallocate(ptr: *mut u8, old_size: uint, size: uint, align: uint) -> *mut u8 {
         if align <= MIN_ALIGN {
@@ -295,9 +723,20 @@ mod imp {
     }
 
     #[inline]
-    pub unsafe fn reallocate_inplace(_ptr: *mut u8, old_size: uint, _size: uint,
+    pub unsafe fn reallocate_zeroed(ptr: *mut u8, old_size: uint, size: uint,
+                                    align: uint) -> *mut u8 {
+        let new_ptr = reallocate(ptr, old_size, size, align);
+        if !new_ptr.is_null() && size > old_size {
+            ptr::zero_memory(new_ptr.offset(old_size as int), size - old_size);
+        }
+        new_ptr
+    }
+
+    #[inline]
+    pub unsafe fn reallocate_inplace(ptr: *mut u8, old_size: uint, size: uint,
                                      _align: uint) -> uint {
-        old_size
+        let available = malloc_usable_size(ptr as *const libc::c_void) as uint;
+        if size <= available { available } else { old_size }
     }
 
     #[inline]
@@ -305,16 +744,38 @@ mod imp {
         libc::free(ptr as *mut libc::c_void)
     }
 
+    // Not a pure query: answering it for a `size`/`align` with no live allocation
+    // requires actually allocating and freeing a block to ask malloc how big it
+    // turned out to be, so this costs a full allocate/deallocate round trip (lock
+    // traffic, possibly a syscall). Callers polling this in a hot path (e.g. a
+    // repeated grow-capacity check) should cache the result against a live
+    // allocation's `ptr` rather than calling this on every check.
     #[inline]
-    pub fn usable_size(size: uint, _align: uint) -> uint {
-        size
+    pub fn usable_size(size: uint, align: uint) -> uint {
+        unsafe {
+            let ptr = allocate(size, align);
+            if ptr.is_null() {
+                size
+            } else {
+                let real_size = malloc_usable_size(ptr as *const libc::c_void) as uint;
+                deallocate(ptr, size, align);
+                real_size
+            }
+        }
     }
 
     pub fn stats_print() {}
+
+    pub fn stats() -> Option<super::Stats> {
+        None
+    }
 }
 
 #[cfg(all(not(external_funcs), not(external_crate), not(jemalloc), windows))]
 mod imp {
+    use core::option::Option;
+    use core::option::Option::None;
+    use core::ptr;
     use libc::{c_void, size_t};
     use libc;
     use super::MIN_ALIGN;
@@ -324,6 +785,17 @@ mod imp {
         fn _aligned_realloc(block: *mut c_void, size: size_t,
                             align: size_t) -> *mut c_void;
         fn _aligned_free(ptr: *mut c_void);
+        fn _msize(memblock: *mut c_void) -> size_t;
+        fn _aligned_msize(memblock: *mut c_void, alignment: size_t, offset: size_t) -> size_t;
+    }
+
+    #[inline]
+    unsafe fn real_usable_size(ptr: *mut c_void, align: uint) -> uint {
+        if align <= MIN_ALIGN {
+            _msize(ptr) as uint
+        } else {
+            _aligned_msize(ptr, align as size_t, 0) as uint
+        }
     }
 
     #[inline]
@@ -335,6 +807,19 @@ mod imp {
         }
     }
 
+    #[inline]
+    pub unsafe fn allocate_zeroed(size: uint, align: uint) -> *mut u8 {
+        if align <= MIN_ALIGN {
+            libc::calloc(size as size_t, 1) as *mut u8
+        } else {
+            let new_ptr = allocate(size, align);
+            if !new_ptr.is_null() {
+                ptr::zero_memory(new_ptr, size);
+            }
+            new_ptr
+        }
+    }
+
     #[inline]
     pub unsafe fn reallocate(ptr: *mut u8, _old_size: uint, size: uint, align: uint) -> *mut u8 {
         if align <= MIN_ALIGN {
@@ -345,9 +830,20 @@ mod imp {
     }
 
     #[inline]
-    pub unsafe fn reallocate_inplace(_ptr: *mut u8, old_size: uint, _size: uint,
-                                     _align: uint) -> uint {
-        old_size
+    pub unsafe fn reallocate_zeroed(ptr: *mut u8, old_size: uint, size: uint,
+                                    align: uint) -> *mut u8 {
+        let new_ptr = reallocate(ptr, old_size, size, align);
+        if !new_ptr.is_null() && size > old_size {
+            ptr::zero_memory(new_ptr.offset(old_size as int), size - old_size);
+        }
+        new_ptr
+    }
+
+    #[inline]
+    pub unsafe fn reallocate_inplace(ptr: *mut u8, old_size: uint, size: uint,
+                                     align: uint) -> uint {
+        let available = real_usable_size(ptr as *mut c_void, align);
+        if size <= available { available } else { old_size }
     }
 
     #[inline]
@@ -359,18 +855,35 @@ mod imp {
         }
     }
 
+    // Not a pure query: see the unix `imp::usable_size` above, which has the same
+    // allocate/deallocate-round-trip cost and the same caution against polling it
+    // from a hot path.
     #[inline]
-    pub fn usable_size(size: uint, _align: uint) -> uint {
-        size
+    pub fn usable_size(size: uint, align: uint) -> uint {
+        unsafe {
+            let ptr = allocate(size, align);
+            if ptr.is_null() {
+                size
+            } else {
+                let real_size = real_usable_size(ptr as *mut c_void, align);
+                deallocate(ptr, size, align);
+                real_size
+            }
+        }
     }
 
     pub fn stats_print() {}
+
+    pub fn stats() -> Option<super::Stats> {
+        None
+    }
 }
 
 #[cfg(test)]
 mod test {
     extern crate test;
     use self::test::Bencher;
+    use core::atomic::{AtomicBool, AtomicUint, INIT_ATOMIC_BOOL, INIT_ATOMIC_UINT, Ordering};
     use core::ptr::PtrExt;
     use heap;
 
@@ -386,6 +899,58 @@ mod test {
         }
     }
 
+    #[test]
+    fn allocate_zeroed_is_zeroed() {
+        unsafe {
+            let size = 128u;
+            let ptr = heap::allocate_zeroed(size, 8);
+            if ptr.is_null() { ::oom() }
+            let mut i = 0u;
+            while i < size {
+                assert_eq!(*ptr.offset(i as int), 0u8);
+                i += 1;
+            }
+            heap::deallocate(ptr, size, 8);
+        }
+    }
+
+    #[test]
+    fn reallocate_inplace_grows_within_usable_size() {
+        unsafe {
+            let size = 16u;
+            let ptr = heap::allocate(size, 8);
+            if ptr.is_null() { ::oom() }
+            let usable = heap::usable_size(size, 8);
+            assert!(usable >= size);
+            // Growing up to the block's own usable size must succeed in place,
+            // since no reallocation is actually required to satisfy it.
+            let ret = heap::reallocate_inplace(ptr, size, usable, 8);
+            assert_eq!(ret, usable);
+            heap::deallocate(ptr, usable, 8);
+        }
+    }
+
+    static OOM_HOOK_RAN: AtomicBool = INIT_ATOMIC_BOOL;
+    static OOM_HOOK_SIZE: AtomicUint = INIT_ATOMIC_UINT;
+    static OOM_HOOK_ALIGN: AtomicUint = INIT_ATOMIC_UINT;
+
+    fn record_oom_hook(size: uint, align: uint) {
+        OOM_HOOK_SIZE.store(size, Ordering::SeqCst);
+        OOM_HOOK_ALIGN.store(align, Ordering::SeqCst);
+        OOM_HOOK_RAN.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn set_oom_hook_runs_registered_hook() {
+        heap::set_oom_hook(record_oom_hook);
+        // Drives the same uint -> fn-pointer transmute that a real OOM would,
+        // without actually exhausting memory (which would abort the process).
+        heap::run_oom_hook(0xdeadu, 64u);
+        assert_eq!(OOM_HOOK_RAN.load(Ordering::SeqCst), true);
+        assert_eq!(OOM_HOOK_SIZE.load(Ordering::SeqCst), 0xdeadu);
+        assert_eq!(OOM_HOOK_ALIGN.load(Ordering::SeqCst), 64u);
+    }
+
     #[bench]
     fn alloc_owned_small(b: &mut Bencher) {
         b.iter(|| {